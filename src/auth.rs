@@ -0,0 +1,183 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header::AUTHORIZATION, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+/// Signing secret for the API's JWTs, loaded once at startup alongside
+/// `DATABASE_URL`. Tokens are minted out-of-band (by the `mint_token`
+/// binary) and just need to verify here.
+#[derive(Clone)]
+pub struct AuthConfig {
+    secret: String,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        let secret = std::env::var("AUTH_SECRET").expect("AUTH_SECRET should exist");
+        Self { secret }
+    }
+
+    /// Signs a token for `sub` at `scope`, expiring `ttl_secs` from now.
+    /// There's no HTTP route for this: exposing token issuance over the API
+    /// would let any caller self-issue a write-scoped credential, so it's
+    /// only reachable from the `mint_token` CLI binary, run out-of-band by
+    /// an operator who already has `AUTH_SECRET`.
+    pub fn issue_token(
+        &self,
+        sub: &str,
+        scope: Scope,
+        ttl_secs: u64,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock should be after the epoch")
+            .as_secs();
+
+        let claims = TokenClaims {
+            sub: sub.to_string(),
+            scope,
+            exp: (now + ttl_secs) as usize,
+        };
+
+        encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(self.secret.as_bytes()),
+        )
+    }
+}
+
+/// Which level of access a token was minted for. `Write` tokens can also hit
+/// `Read`-scoped routes; `Read` tokens can't mutate anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    Write,
+}
+
+/// Identity carried by a bearer token: who it was issued to, what it's
+/// allowed to do, and when it expires.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenClaims {
+    pub sub: String,
+    pub scope: Scope,
+    pub exp: usize,
+}
+
+fn verify_scope(req: &Request<Body>, config: &AuthConfig, required: Scope) -> Result<(), StatusCode> {
+    let header = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let token = header
+        .strip_prefix("Bearer ")
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let claims = decode::<TokenClaims>(
+        token,
+        &DecodingKey::from_secret(config.secret.as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )
+    .map_err(|_| StatusCode::UNAUTHORIZED)?
+    .claims;
+
+    if required == Scope::Write && claims.scope != Scope::Write {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(())
+}
+
+/// Middleware for mutating routes: `create_user`, `create_wager`,
+/// `close_wager`, `add_user_to_wager`, `remove_user_from_wager`,
+/// `schedule_wager`.
+pub async fn require_write_scope(
+    State(config): State<AuthConfig>,
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, StatusCode> {
+    verify_scope(&req, &config, Scope::Write)?;
+    Ok(next.run(req).await)
+}
+
+/// Lighter middleware for read-only routes so a dashboard can query balances
+/// without holding a token that can mint or settle wagers.
+pub async fn require_read_scope(
+    State(config): State<AuthConfig>,
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, StatusCode> {
+    verify_scope(&req, &config, Scope::Read)?;
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer_request(token: &str) -> Request<Body> {
+        Request::builder()
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn write_token_passes_write_check() {
+        let config = AuthConfig {
+            secret: "test-secret".to_string(),
+        };
+        let token = config.issue_token("bot", Scope::Write, 60).unwrap();
+
+        assert!(verify_scope(&bearer_request(&token), &config, Scope::Write).is_ok());
+    }
+
+    #[test]
+    fn read_token_fails_write_check() {
+        let config = AuthConfig {
+            secret: "test-secret".to_string(),
+        };
+        let token = config.issue_token("dashboard", Scope::Read, 60).unwrap();
+
+        assert_eq!(
+            verify_scope(&bearer_request(&token), &config, Scope::Write),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let config = AuthConfig {
+            secret: "test-secret".to_string(),
+        };
+        // signed directly (bypassing `issue_token`'s forward-only TTL) so the
+        // token is unambiguously expired, rather than relying on a short TTL
+        // racing `jsonwebtoken`'s ~60s default validation leeway
+        let claims = TokenClaims {
+            sub: "bot".to_string(),
+            scope: Scope::Write,
+            exp: 1,
+        };
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(config.secret.as_bytes()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            verify_scope(&bearer_request(&token), &config, Scope::Write),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+}
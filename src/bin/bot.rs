@@ -0,0 +1,453 @@
+use std::env;
+
+use bean_bucks_api::{self, cache::UserCache, jobs, JoinError, SettleError};
+use dotenv::dotenv;
+use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateEmbed},
+    model::{
+        application::{
+            command::Command,
+            interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOption},
+                Interaction, InteractionResponseType,
+            },
+        },
+        gateway::Ready,
+        id::GuildId,
+        prelude::command::CommandOptionType,
+    },
+    prelude::*,
+};
+use sqlx::{mysql::MySqlPoolOptions, MySql, Pool};
+
+struct PoolKey;
+impl TypeMapKey for PoolKey {
+    type Value = Pool<MySql>;
+}
+
+struct CacheKey;
+impl TypeMapKey for CacheKey {
+    type Value = UserCache;
+}
+
+struct Handler;
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        println!("{} is connected!", ready.user.name);
+
+        let guild_id = GuildId(
+            env::var("DISCORD_GUILD_ID")
+                .expect("DISCORD_GUILD_ID should exist")
+                .parse()
+                .expect("DISCORD_GUILD_ID should be a valid guild id"),
+        );
+
+        guild_id
+            .set_application_commands(&ctx.http, |commands| {
+                commands
+                    .create_application_command(|command| balance_command(command))
+                    .create_application_command(|command| wager_command(command))
+            })
+            .await
+            .expect("expected slash commands to register");
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Interaction::ApplicationCommand(command) = interaction else {
+            return;
+        };
+
+        let result = match command.data.name.as_str() {
+            "balance" => handle_balance(&ctx, &command).await,
+            "wager" => handle_wager(&ctx, &command).await,
+            other => Err(format!("unknown command: {other}")),
+        };
+
+        if let Err(message) = result {
+            respond(&ctx, &command, &message).await;
+        }
+    }
+}
+
+fn balance_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command.name("balance").description("Check your bean bucks balance")
+}
+
+fn wager_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name("wager")
+        .description("Manage bean bucks wagers")
+        .create_option(|option| {
+            option
+                .name("create")
+                .description("Create a new wager")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|o| {
+                    o.name("amount")
+                        .description("Stake each participant puts in")
+                        .kind(CommandOptionType::Integer)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("join")
+                .description("Join a wager")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|o| {
+                    o.name("id")
+                        .description("Wager id")
+                        .kind(CommandOptionType::Integer)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("leave")
+                .description("Leave a wager")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|o| {
+                    o.name("id")
+                        .description("Wager id")
+                        .kind(CommandOptionType::Integer)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("close")
+                .description("Settle a wager")
+                .kind(CommandOptionType::SubCommand)
+                .create_sub_option(|o| {
+                    o.name("id")
+                        .description("Wager id")
+                        .kind(CommandOptionType::Integer)
+                        .required(true)
+                })
+                .create_sub_option(|o| {
+                    o.name("winners")
+                        .description("Winning participants, e.g. @user1 @user2")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|o| {
+                    o.name("losers")
+                        .description("Losing participants, e.g. @user1 @user2")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+        })
+}
+
+async fn pool_from_ctx(ctx: &Context) -> Pool<MySql> {
+    let data = ctx.data.read().await;
+    data.get::<PoolKey>()
+        .expect("expected a mysql pool in client data")
+        .clone()
+}
+
+async fn cache_from_ctx(ctx: &Context) -> UserCache {
+    let data = ctx.data.read().await;
+    data.get::<CacheKey>()
+        .expect("expected a user cache in client data")
+        .clone()
+}
+
+async fn handle_balance(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+) -> Result<(), String> {
+    let pool = pool_from_ctx(ctx).await;
+    let cache = cache_from_ctx(ctx).await;
+    let discord_id = *command.user.id.as_u64();
+    let user = bean_bucks_api::get_or_create_user(&pool, &cache, discord_id, &command.user.name)
+        .await
+        .map_err(|e| format!("failed to look up balance: {e}"))?;
+
+    respond(
+        ctx,
+        command,
+        &format!("**{}** has **{}** bean bucks", user.user_name, user.bucks),
+    )
+    .await;
+    Ok(())
+}
+
+async fn handle_wager(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+) -> Result<(), String> {
+    let sub = command
+        .data
+        .options
+        .first()
+        .ok_or("missing wager subcommand")?;
+
+    match sub.name.as_str() {
+        "create" => handle_wager_create(ctx, command, &sub.options).await,
+        "join" => handle_wager_join(ctx, command, &sub.options).await,
+        "leave" => handle_wager_leave(ctx, command, &sub.options).await,
+        "close" => handle_wager_close(ctx, command, &sub.options).await,
+        other => Err(format!("unknown wager subcommand: {other}")),
+    }
+}
+
+fn option_i64(options: &[CommandDataOption], name: &str) -> Option<i64> {
+    options
+        .iter()
+        .find(|o| o.name == name)?
+        .value
+        .as_ref()?
+        .as_i64()
+}
+
+fn option_str<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|o| o.name == name)?
+        .value
+        .as_ref()?
+        .as_str()
+}
+
+// pulls every `<@id>` / `<@!id>` mention out of a raw string like "@a @b"
+fn parse_mentioned_discord_ids(raw: &str) -> Vec<u64> {
+    raw.split_whitespace()
+        .filter_map(|token| {
+            token
+                .trim_start_matches("<@")
+                .trim_start_matches('!')
+                .trim_end_matches('>')
+                .parse()
+                .ok()
+        })
+        .collect()
+}
+
+async fn handle_wager_create(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<(), String> {
+    let amount = option_i64(options, "amount").ok_or("missing amount")? as i32;
+    let pool = pool_from_ctx(ctx).await;
+    let (wager, _outcomes) = bean_bucks_api::create_wager(&pool, amount, &[])
+        .await
+        .map_err(|e| format!("failed to create wager: {e}"))?;
+
+    respond(
+        ctx,
+        command,
+        &format!("Created wager **#{}** for **{}** bean bucks", wager.id, wager.amount),
+    )
+    .await;
+    Ok(())
+}
+
+async fn handle_wager_join(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<(), String> {
+    let wager_id = option_i64(options, "id").ok_or("missing id")? as i32;
+    let pool = pool_from_ctx(ctx).await;
+    let cache = cache_from_ctx(ctx).await;
+    let discord_id = *command.user.id.as_u64();
+
+    match bean_bucks_api::join_wager(
+        &pool,
+        &cache,
+        discord_id,
+        &command.user.name,
+        wager_id,
+        None,
+        None,
+    )
+    .await
+    {
+        Ok(_) => {
+            respond(ctx, command, &format!("Joined wager **#{wager_id}**")).await;
+            Ok(())
+        }
+        Err(JoinError::AlreadyJoined) => {
+            respond(ctx, command, "You're already in that wager").await;
+            Ok(())
+        }
+        Err(JoinError::InsufficientFunds) => {
+            respond(ctx, command, "You don't have enough bean bucks to join").await;
+            Ok(())
+        }
+        Err(JoinError::InvalidStake) => {
+            respond(ctx, command, "Stake must be greater than zero").await;
+            Ok(())
+        }
+        Err(JoinError::WagerNotFound) => {
+            respond(ctx, command, "That wager doesn't exist").await;
+            Ok(())
+        }
+        Err(JoinError::Database(e)) => Err(format!("failed to join wager: {e}")),
+    }
+}
+
+async fn handle_wager_leave(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<(), String> {
+    let wager_id = option_i64(options, "id").ok_or("missing id")? as i32;
+    let pool = pool_from_ctx(ctx).await;
+    let discord_id = *command.user.id.as_u64();
+
+    match bean_bucks_api::leave_wager(&pool, discord_id, wager_id).await {
+        Ok(()) => {
+            respond(ctx, command, &format!("Left wager **#{wager_id}**")).await;
+            Ok(())
+        }
+        Err(JoinError::WagerNotFound) => {
+            respond(ctx, command, "You aren't in that wager").await;
+            Ok(())
+        }
+        Err(e) => Err(format!("failed to leave wager: {e:?}")),
+    }
+}
+
+async fn handle_wager_close(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<(), String> {
+    let wager_id = option_i64(options, "id").ok_or("missing id")? as i32;
+    let winners = parse_mentioned_discord_ids(option_str(options, "winners").ok_or("missing winners")?);
+    let losers = parse_mentioned_discord_ids(option_str(options, "losers").ok_or("missing losers")?);
+
+    let pool = pool_from_ctx(ctx).await;
+    let cache = cache_from_ctx(ctx).await;
+    match bean_bucks_api::settle_wager(&pool, &cache, wager_id, &winners, &losers).await {
+        Ok(result) => {
+            respond_embed(ctx, command, |embed| {
+                embed
+                    .title(format!("Wager #{} closed", result.wager.id))
+                    .field(
+                        "Winners",
+                        result
+                            .winners
+                            .iter()
+                            .map(|u| format!("{} -> {} bucks", u.user_name, u.bucks))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        false,
+                    )
+                    .field(
+                        "Losers",
+                        result
+                            .losers
+                            .iter()
+                            .map(|u| format!("{} -> {} bucks", u.user_name, u.bucks))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        false,
+                    )
+            })
+            .await;
+            Ok(())
+        }
+        Err(SettleError::WagerNotFound) => {
+            respond(ctx, command, "That wager doesn't exist").await;
+            Ok(())
+        }
+        Err(SettleError::AlreadyClosed) => {
+            respond(ctx, command, "That wager is already closed").await;
+            Ok(())
+        }
+        Err(SettleError::NoWinningStake) => {
+            respond(
+                ctx,
+                command,
+                "Nobody staked on the winners, so there's no one to pay out — check the mentions and try again",
+            )
+            .await;
+            Ok(())
+        }
+        Err(SettleError::Database(e)) => Err(format!("failed to close wager: {e}")),
+    }
+}
+
+async fn respond(ctx: &Context, command: &ApplicationCommandInteraction, message: &str) {
+    if let Err(e) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| data.content(message))
+        })
+        .await
+    {
+        println!("failed to respond to slash command: {e}");
+    }
+}
+
+async fn respond_embed(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    build: impl FnOnce(&mut CreateEmbed) -> &mut CreateEmbed,
+) {
+    if let Err(e) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| data.embed(build))
+        })
+        .await
+    {
+        println!("failed to respond to slash command: {e}");
+    }
+}
+
+#[allow(dead_code)]
+async fn unregister_all(ctx: &Context) {
+    // handy for local development when command shapes change
+    if let Ok(commands) = Command::get_global_application_commands(&ctx.http).await {
+        for command in commands {
+            let _ = Command::delete_global_application_command(&ctx.http, command.id).await;
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    let token = env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN should exist");
+    let database_url = env::var("DATABASE_URL").expect("Database URL should exist");
+    let pool = MySqlPoolOptions::new()
+        .max_connections(5)
+        .connect(&database_url)
+        .await
+        .expect("expected to connect to the database");
+
+    let cache = UserCache::default();
+    // the bot holds its own `UserCache`, independent of the axum API's — run
+    // the same job worker here too so a wager this process's worker claims
+    // and auto-settles invalidates this process's cache directly, instead of
+    // only the API's
+    jobs::spawn_worker(pool.clone(), cache.clone());
+
+    let intents = GatewayIntents::non_privileged();
+    let mut client = Client::builder(&token, intents)
+        .event_handler(Handler)
+        .await
+        .expect("expected client to build");
+
+    {
+        let mut data = client.data.write().await;
+        data.insert::<PoolKey>(pool);
+        data.insert::<CacheKey>(cache);
+    }
+
+    if let Err(e) = client.start().await {
+        println!("client error: {e}");
+    }
+}
@@ -0,0 +1,43 @@
+//! Mints a bearer token for the HTTP API. Run out-of-band by an operator who
+//! has `AUTH_SECRET` set; there's deliberately no HTTP route for this, since
+//! exposing it would let any caller self-issue a write-scoped token.
+//!
+//! Usage: mint_token <sub> <read|write> [ttl_secs]
+
+use std::env;
+
+use bean_bucks_api::auth::{AuthConfig, Scope};
+use dotenv::dotenv;
+
+fn main() {
+    dotenv().ok();
+
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: mint_token <sub> <read|write> [ttl_secs]");
+        std::process::exit(1);
+    }
+    let sub = &args[1];
+    let scope = &args[2];
+
+    let scope = match scope.as_str() {
+        "read" => Scope::Read,
+        "write" => Scope::Write,
+        other => {
+            eprintln!("unknown scope '{other}', expected 'read' or 'write'");
+            std::process::exit(1);
+        }
+    };
+
+    let ttl_secs: u64 = args
+        .get(3)
+        .map(|ttl| ttl.parse().expect("ttl_secs should be a number"))
+        .unwrap_or(60 * 60 * 24 * 365);
+
+    let config = AuthConfig::from_env();
+    let token = config
+        .issue_token(sub, scope, ttl_secs)
+        .expect("token should sign successfully");
+
+    println!("{token}");
+}
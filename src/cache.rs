@@ -0,0 +1,173 @@
+//! Shared, bounded, TTL'd cache of `User` rows keyed by `discord_id`, so a
+//! burst of wager activity doesn't hammer the 5-connection pool with one
+//! `SELECT` per participant.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use sqlx::{MySql, Pool};
+use tokio::sync::RwLock;
+
+use crate::User;
+
+const DEFAULT_CAPACITY: usize = 1000;
+const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+struct CachedUser {
+    user: User,
+    inserted_at: Instant,
+}
+
+struct TtlCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<u64, CachedUser>,
+}
+
+impl TtlCache {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&self, discord_id: u64) -> Option<User> {
+        self.entries.get(&discord_id).and_then(|cached| {
+            if cached.inserted_at.elapsed() < self.ttl {
+                Some(cached.user.clone())
+            } else {
+                None
+            }
+        })
+    }
+
+    fn insert(&mut self, user: User) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&user.discord_id) {
+            if let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.inserted_at)
+                .map(|(discord_id, _)| *discord_id)
+            {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(
+            user.discord_id,
+            CachedUser {
+                user,
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+
+    fn remove(&mut self, discord_id: u64) {
+        self.entries.remove(&discord_id);
+    }
+}
+
+#[derive(Clone)]
+pub struct UserCache {
+    inner: Arc<RwLock<TtlCache>>,
+}
+
+impl UserCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(TtlCache::new(capacity, ttl))),
+        }
+    }
+
+    /// Returns the cached row if it's fresh, otherwise loads it from MySQL
+    /// and populates the cache.
+    pub async fn get_or_fetch(
+        &self,
+        pool: &Pool<MySql>,
+        discord_id: u64,
+    ) -> Result<Option<User>, sqlx::Error> {
+        if let Some(user) = self.inner.read().await.get(discord_id) {
+            return Ok(Some(user));
+        }
+
+        let user = sqlx::query_as::<_, User>("SELECT * FROM user WHERE discord_id = ?")
+            .bind(discord_id)
+            .fetch_optional(pool)
+            .await?;
+
+        if let Some(user) = &user {
+            self.inner.write().await.insert(user.clone());
+        }
+
+        Ok(user)
+    }
+
+    /// Must be called after any balance mutation so stale bucks never leak.
+    pub async fn put(&self, user: User) {
+        self.inner.write().await.insert(user);
+    }
+
+    pub async fn invalidate(&self, discord_id: u64) {
+        self.inner.write().await.remove(discord_id);
+    }
+}
+
+impl Default for UserCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(discord_id: u64) -> User {
+        User {
+            id: discord_id as i32,
+            discord_id,
+            user_name: format!("user-{discord_id}"),
+            bucks: 500,
+        }
+    }
+
+    #[test]
+    fn get_returns_none_once_an_entry_is_older_than_the_ttl() {
+        let mut cache = TtlCache::new(10, Duration::from_millis(10));
+        cache.insert(user(1));
+        assert!(cache.get(1).is_some());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(cache.get(1).is_none());
+    }
+
+    #[test]
+    fn insert_evicts_the_oldest_entry_once_over_capacity() {
+        let mut cache = TtlCache::new(2, Duration::from_secs(60));
+        cache.insert(user(1));
+        std::thread::sleep(Duration::from_millis(5));
+        cache.insert(user(2));
+        std::thread::sleep(Duration::from_millis(5));
+        // over capacity: the oldest entry (1) is evicted to make room
+        cache.insert(user(3));
+
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(2).is_some());
+        assert!(cache.get(3).is_some());
+    }
+
+    #[test]
+    fn insert_does_not_evict_when_refreshing_an_existing_entry() {
+        let mut cache = TtlCache::new(1, Duration::from_secs(60));
+        cache.insert(user(1));
+        // already at capacity, but this discord_id is already present, so
+        // there's nothing to evict
+        cache.insert(user(1));
+
+        assert!(cache.get(1).is_some());
+    }
+}
@@ -0,0 +1,179 @@
+//! Durable job queue backing `job_queue`: lets a wager be scheduled to
+//! auto-settle at a future time, and makes settlement retries crash-safe
+//! across worker restarts.
+
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+
+use crate::{cache::UserCache, settle_wager, SettleError};
+
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(60);
+const HEARTBEAT_REFRESH_INTERVAL: Duration = Duration::from_secs(20);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CloseWagerJobPayload {
+    pub wager_id: i32,
+    pub winning_user_discord_ids: Vec<u64>,
+    pub losing_user_discord_ids: Vec<u64>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct JobRow {
+    id: i32,
+    kind: String,
+    payload: String,
+}
+
+/// Inserts a `close_wager` job to run at `run_at`.
+pub async fn schedule_close_wager(
+    pool: &Pool<MySql>,
+    payload: &CloseWagerJobPayload,
+    run_at: NaiveDateTime,
+) -> Result<i32, sqlx::Error> {
+    let payload_json = serde_json::to_string(payload).expect("job payload should serialize");
+    let id = sqlx::query(
+        "INSERT INTO job_queue(kind, payload, status, attempts, run_at) VALUES (?, ?, 'new', 0, ?)",
+    )
+    .bind("close_wager")
+    .bind(payload_json)
+    .bind(run_at)
+    .execute(pool)
+    .await?
+    .last_insert_id();
+
+    Ok(id as i32)
+}
+
+// atomically claims the oldest due job so two workers never grab the same row
+async fn claim_job(pool: &Pool<MySql>) -> Result<Option<JobRow>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let job = sqlx::query_as::<_, JobRow>(
+        "SELECT id, kind, payload FROM job_queue \
+         WHERE status = 'new' AND run_at <= NOW() ORDER BY run_at LIMIT 1 FOR UPDATE",
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(job) = &job {
+        sqlx::query("UPDATE job_queue SET status = 'running', heartbeat = NOW() WHERE id = ?")
+            .bind(job.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(job)
+}
+
+// re-stamps `heartbeat` every `HEARTBEAT_REFRESH_INTERVAL` until aborted, so
+// `reap_stale_jobs` doesn't mistake a job that's still running for one whose
+// worker crashed
+fn spawn_heartbeat_refresh(pool: Pool<MySql>, job_id: i32) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(HEARTBEAT_REFRESH_INTERVAL).await;
+            let _ = sqlx::query(
+                "UPDATE job_queue SET heartbeat = NOW() WHERE id = ? AND status = 'running'",
+            )
+            .bind(job_id)
+            .execute(&pool)
+            .await;
+        }
+    })
+}
+
+async fn run_job(pool: &Pool<MySql>, cache: &UserCache, job: &JobRow) -> Result<(), String> {
+    let heartbeat_refresh = spawn_heartbeat_refresh(pool.clone(), job.id);
+
+    // a block, not `?`, so a malformed payload returns from here rather than
+    // skipping the `abort()` below by propagating out of `run_job` itself
+    let result: Result<(), String> = match job.kind.as_str() {
+        "close_wager" => {
+            async {
+                let payload: CloseWagerJobPayload = serde_json::from_str(&job.payload)
+                    .map_err(|e| format!("malformed job payload: {e}"))?;
+                match settle_wager(
+                    pool,
+                    cache,
+                    payload.wager_id,
+                    &payload.winning_user_discord_ids,
+                    &payload.losing_user_discord_ids,
+                )
+                .await
+                {
+                    Ok(_) | Err(SettleError::AlreadyClosed) => Ok(()),
+                    Err(e) => Err(format!("failed to settle scheduled wager: {e:?}")),
+                }
+            }
+            .await
+        }
+        other => Err(format!("unknown job kind: {other}")),
+    };
+
+    heartbeat_refresh.abort();
+    result
+}
+
+async fn process_due_jobs(pool: &Pool<MySql>, cache: &UserCache) {
+    loop {
+        let job = match claim_job(pool).await {
+            Ok(Some(job)) => job,
+            Ok(None) => return,
+            Err(e) => {
+                println!("failed to claim job: {e}");
+                return;
+            }
+        };
+
+        match run_job(pool, cache, &job).await {
+            Ok(()) => {
+                let _ = sqlx::query("DELETE FROM job_queue WHERE id = ?")
+                    .bind(job.id)
+                    .execute(pool)
+                    .await;
+            }
+            Err(message) => {
+                println!("job {} failed: {message}", job.id);
+                // clear status back to 'new' so it's picked up again, and track attempts
+                let _ = sqlx::query(
+                    "UPDATE job_queue SET status = 'new', attempts = attempts + 1 WHERE id = ?",
+                )
+                .bind(job.id)
+                .execute(pool)
+                .await;
+            }
+        }
+    }
+}
+
+// requeues any `running` job whose worker crashed mid-heartbeat
+async fn reap_stale_jobs(pool: &Pool<MySql>) {
+    let cutoff = Utc::now().naive_utc() - chrono::Duration::from_std(HEARTBEAT_TIMEOUT).unwrap();
+    let result = sqlx::query(
+        "UPDATE job_queue SET status = 'new' WHERE status = 'running' AND heartbeat < ?",
+    )
+    .bind(cutoff)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        println!("failed to reap stale jobs: {e}");
+    }
+}
+
+/// Spawns the background loop that polls `job_queue` for due jobs and reaps
+/// jobs abandoned by a crashed worker.
+pub fn spawn_worker(pool: Pool<MySql>, cache: UserCache) {
+    tokio::spawn(async move {
+        loop {
+            reap_stale_jobs(&pool).await;
+            process_due_jobs(&pool, &cache).await;
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
@@ -0,0 +1,702 @@
+//! Core domain logic shared between the axum HTTP API (`src/main.rs`) and
+//! the Discord bot (`src/bin/bot.rs`), so both front-ends settle wagers and
+//! manage balances the same way.
+
+pub mod auth;
+pub mod cache;
+pub mod jobs;
+
+use cache::UserCache;
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool, Transaction};
+
+pub const STARTING_BUCKS: i32 = 500;
+
+#[derive(sqlx::FromRow, Debug, Serialize, Clone)]
+pub struct User {
+    pub id: i32,
+    pub discord_id: u64,
+    pub user_name: String,
+    pub bucks: i32,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct Wager {
+    pub id: i32,
+    pub amount: i32,
+    pub closed: bool,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize, Deserialize, Clone)]
+pub struct Outcome {
+    pub id: i32,
+    pub wager_id: i32,
+    pub name: String,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct UserWager {
+    pub id: i32,
+    pub wager_id: i32,
+    pub user_id: i32,
+    // null until settlement classifies this entry (see `settle_wager`'s
+    // implicit "Winners"/"Losers" outcomes) for a flat wager; set at join
+    // time for a market created through `create_wager`'s `outcome_names`
+    pub outcome_id: Option<i32>,
+    pub stake: Option<i32>,
+    // net bucks change for this participant, set once the wager is settled
+    pub delta: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SucessfullCloseWager {
+    pub winners: Vec<User>,
+    pub losers: Vec<User>,
+    pub wager: Wager,
+    // only set when this wager was settled as a market via `close_market`
+    pub winning_outcome: Option<Outcome>,
+}
+
+#[derive(Debug)]
+pub enum SettleError {
+    WagerNotFound,
+    AlreadyClosed,
+    // the winning side staked nothing (no entries on the outcome, or every
+    // entry staked 0): there's no one to pay the losers' stakes to, so
+    // `settle_locked` refuses rather than letting `distribute_proportional`'s
+    // zero-total guard silently zero out every payout while losers still get
+    // charged
+    NoWinningStake,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for SettleError {
+    fn from(err: sqlx::Error) -> Self {
+        SettleError::Database(err)
+    }
+}
+
+// splits `pot` bucks across `stakes` proportionally to each entry's share of
+// the total staked, handing the indivisible remainder to the first entry so
+// the books balance exactly
+pub fn distribute_proportional(pot: i32, stakes: &[i32]) -> Vec<i32> {
+    let total: i32 = stakes.iter().sum();
+    if total <= 0 {
+        return vec![0; stakes.len()];
+    }
+    let mut payouts: Vec<i32> = stakes.iter().map(|stake| pot * stake / total).collect();
+    let remainder = pot - payouts.iter().sum::<i32>();
+    if let Some(first) = payouts.first_mut() {
+        *first += remainder;
+    }
+    payouts
+}
+
+// splits `pot` bucks evenly across `winner_count` winners; the special case
+// of `distribute_proportional` where every winner staked the same amount
+pub fn distribute_pot(pot: i32, winner_count: i32) -> Vec<i32> {
+    if winner_count <= 0 {
+        return vec![];
+    }
+    distribute_proportional(pot, &vec![1; winner_count as usize])
+}
+
+// the DB-free arithmetic core of `settle_locked`: classifies `entries` into
+// winners/losers of `winning_outcome_id` and works out each winner's payout,
+// without touching anything. Pulled out of `settle_locked` so the conservation
+// invariant can be tested directly against this, rather than a
+// reimplementation of the same math in the test body.
+fn compute_settlement(
+    entries: &[UserWager],
+    winning_outcome_id: i32,
+) -> Result<(Vec<(&UserWager, i32)>, Vec<&UserWager>), SettleError> {
+    let winning_entries: Vec<&UserWager> = entries
+        .iter()
+        .filter(|entry| entry.outcome_id == Some(winning_outcome_id))
+        .collect();
+    let losing_entries: Vec<&UserWager> = entries
+        .iter()
+        .filter(|entry| entry.outcome_id.is_some() && entry.outcome_id != Some(winning_outcome_id))
+        .collect();
+
+    // the pot is every losing entry's stake, split across winners
+    // proportionally to their own stake share, with the remainder going to
+    // the first winner so no bucks are lost to integer division
+    let pot: i32 = losing_entries.iter().filter_map(|entry| entry.stake).sum();
+    let winning_stakes: Vec<i32> = winning_entries
+        .iter()
+        .filter_map(|entry| entry.stake)
+        .collect();
+
+    // if there's a pot to pay out but no one staked anything on the winning
+    // side, `distribute_proportional` can't split it across anyone; refuse
+    // rather than silently forfeiting the losers' stakes to nowhere
+    if pot > 0 && winning_stakes.iter().sum::<i32>() <= 0 {
+        return Err(SettleError::NoWinningStake);
+    }
+
+    let payouts = distribute_proportional(pot, &winning_stakes);
+    let winner_payouts: Vec<(&UserWager, i32)> =
+        winning_entries.into_iter().zip(payouts).collect();
+
+    Ok((winner_payouts, losing_entries))
+}
+
+// the one settlement engine shared by the legacy flat wager and
+// `close_market`: pays the entries staked on `winning_outcome_id`
+// proportionally to their share of the losing entries' total stake, and
+// forfeits (clamped at zero) every other classified entry's stake. Entries
+// with no `outcome_id` yet (an unclassified legacy participant) are ignored.
+async fn settle_locked(
+    tx: &mut Transaction<'_, MySql>,
+    entries: &[UserWager],
+    mut users: Vec<User>,
+    winning_outcome_id: i32,
+) -> Result<(Vec<User>, Vec<User>), SettleError> {
+    let (winner_payouts, losing_entries) = compute_settlement(entries, winning_outcome_id)?;
+
+    let mut winning_users: Vec<User> = vec![];
+    for (entry, payout) in winner_payouts {
+        let user = users
+            .iter_mut()
+            .find(|user| user.id == entry.user_id)
+            .expect("user_wager should reference an existing user");
+        user.bucks += payout;
+        sqlx::query("UPDATE user SET bucks = ? WHERE id = ?")
+            .bind(user.bucks)
+            .bind(user.id)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("UPDATE user_wager SET delta = ? WHERE id = ?")
+            .bind(payout)
+            .bind(entry.id)
+            .execute(&mut **tx)
+            .await?;
+        winning_users.push(user.clone());
+    }
+
+    // take the stake from the losers; if they don't have enough, put them at
+    // zero
+    let mut losing_users: Vec<User> = vec![];
+    for entry in losing_entries {
+        let user = users
+            .iter_mut()
+            .find(|user| user.id == entry.user_id)
+            .expect("user_wager should reference an existing user");
+        let stake = entry.stake.unwrap_or(0);
+        let bucks_before = user.bucks;
+        user.bucks = (user.bucks - stake).max(0);
+        sqlx::query("UPDATE user SET bucks = ? WHERE id = ?")
+            .bind(user.bucks)
+            .bind(user.id)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("UPDATE user_wager SET delta = ? WHERE id = ?")
+            .bind(user.bucks - bucks_before)
+            .bind(entry.id)
+            .execute(&mut **tx)
+            .await?;
+        losing_users.push(user.clone());
+    }
+
+    Ok((winning_users, losing_users))
+}
+
+// locks the wager row (`FOR UPDATE`, so a concurrent settlement can't read a
+// stale balance and clobber this one) and checks it isn't already closed
+async fn lock_wager(
+    tx: &mut Transaction<'_, MySql>,
+    wager_id: i32,
+) -> Result<Wager, SettleError> {
+    let wager: Wager = sqlx::query_as::<_, Wager>("SELECT * FROM wager WHERE id = ? FOR UPDATE")
+        .bind(wager_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or(SettleError::WagerNotFound)?;
+
+    if wager.closed {
+        return Err(SettleError::AlreadyClosed);
+    }
+
+    Ok(wager)
+}
+
+// loads every participant of `wager_id` alongside their user row, locking
+// the user rows (`FOR UPDATE`) so a concurrent wager can't race the
+// settlement's "floor at zero" clamp
+async fn load_entries_locked(
+    tx: &mut Transaction<'_, MySql>,
+    wager_id: i32,
+) -> Result<(Vec<UserWager>, Vec<User>), sqlx::Error> {
+    let entries: Vec<UserWager> =
+        sqlx::query_as::<_, UserWager>("SELECT * FROM user_wager WHERE wager_id = ?")
+            .bind(wager_id)
+            .fetch_all(&mut **tx)
+            .await?;
+
+    let user_ids: Vec<i32> = entries.iter().map(|entry| entry.user_id).collect();
+    let users: Vec<User> = if user_ids.is_empty() {
+        vec![]
+    } else {
+        let placeholders = vec!["?"; user_ids.len()].join(",");
+        let mut query = sqlx::query_as::<_, User>(&format!(
+            "SELECT * FROM user WHERE id IN ({placeholders}) FOR UPDATE"
+        ));
+        for user_id in &user_ids {
+            query = query.bind(user_id);
+        }
+        query.fetch_all(&mut **tx).await?
+    };
+
+    Ok((entries, users))
+}
+
+// returns `wager_id`'s outcome named `name`, creating it if this is the
+// first time it's needed
+async fn ensure_outcome(
+    tx: &mut Transaction<'_, MySql>,
+    wager_id: i32,
+    name: &str,
+) -> Result<Outcome, sqlx::Error> {
+    if let Some(outcome) =
+        sqlx::query_as::<_, Outcome>("SELECT * FROM outcome WHERE wager_id = ? AND name = ?")
+            .bind(wager_id)
+            .bind(name)
+            .fetch_optional(&mut **tx)
+            .await?
+    {
+        return Ok(outcome);
+    }
+
+    let id = sqlx::query("INSERT INTO outcome(wager_id, name) VALUES (?, ?)")
+        .bind(wager_id)
+        .bind(name)
+        .execute(&mut **tx)
+        .await?
+        .last_insert_id();
+
+    Ok(Outcome {
+        id: id as i32,
+        wager_id,
+        name: name.to_string(),
+    })
+}
+
+/// Settles a flat (non-market) wager: winners and losers are named by discord
+/// id rather than chosen at join time, so this classifies every participant
+/// into one of two outcomes synthesized lazily on the wager ("Winners" and
+/// "Losers") and then settles through the same engine `close_market` uses —
+/// the flat wager is just the two-outcome special case of a market, with
+/// every participant staking the wager's fixed `amount`.
+pub async fn settle_wager(
+    pool: &Pool<MySql>,
+    cache: &UserCache,
+    wager_id: i32,
+    winning_discord_ids: &[u64],
+    losing_discord_ids: &[u64],
+) -> Result<SucessfullCloseWager, SettleError> {
+    let mut tx = pool.begin().await?;
+
+    let wager = lock_wager(&mut tx, wager_id).await?;
+    let (mut entries, users) = load_entries_locked(&mut tx, wager_id).await?;
+
+    let winners_outcome = ensure_outcome(&mut tx, wager_id, "Winners").await?;
+    let losers_outcome = ensure_outcome(&mut tx, wager_id, "Losers").await?;
+
+    // the users passed in for winners and losers should be checked to make
+    // sure they were added to the wager. If they were not, they should be
+    // ignored
+    for entry in &mut entries {
+        let discord_id = users
+            .iter()
+            .find(|user| user.id == entry.user_id)
+            .map(|user| user.discord_id);
+        let Some(discord_id) = discord_id else {
+            continue;
+        };
+
+        let outcome_id = if winning_discord_ids.contains(&discord_id) {
+            winners_outcome.id
+        } else if losing_discord_ids.contains(&discord_id) {
+            losers_outcome.id
+        } else {
+            continue;
+        };
+
+        entry.outcome_id = Some(outcome_id);
+        entry.stake = Some(wager.amount);
+        sqlx::query("UPDATE user_wager SET outcome_id = ?, stake = ? WHERE id = ?")
+            .bind(outcome_id)
+            .bind(wager.amount)
+            .bind(entry.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let (winning_users, losing_users) =
+        settle_locked(&mut tx, &entries, users, winners_outcome.id).await?;
+
+    sqlx::query("UPDATE wager SET closed = true WHERE id = ?")
+        .bind(wager_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    // the balance mutations above just committed; refresh the cache so
+    // nobody reads a stale balance for these users
+    for user in winning_users.iter().chain(losing_users.iter()) {
+        cache.put(user.clone()).await;
+    }
+
+    Ok(SucessfullCloseWager {
+        winners: winning_users,
+        losers: losing_users,
+        wager: Wager {
+            closed: true,
+            ..wager
+        },
+        winning_outcome: None,
+    })
+}
+
+#[derive(Debug)]
+pub enum JoinError {
+    AlreadyJoined,
+    InsufficientFunds,
+    // `stake` was zero or negative, either supplied directly by a caller or
+    // (when `None`) inherited from a non-positive `wager.amount`
+    InvalidStake,
+    WagerNotFound,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for JoinError {
+    fn from(err: sqlx::Error) -> Self {
+        JoinError::Database(err)
+    }
+}
+
+/// Looks a user up by discord id (through `cache`), auto-provisioning them
+/// with `STARTING_BUCKS` if this is the first time we've seen them.
+pub async fn get_or_create_user(
+    pool: &Pool<MySql>,
+    cache: &UserCache,
+    discord_id: u64,
+    user_name: &str,
+) -> Result<User, sqlx::Error> {
+    if let Some(user) = cache.get_or_fetch(pool, discord_id).await? {
+        return Ok(user);
+    }
+
+    let id = sqlx::query("INSERT INTO user(discord_id, user_name, bucks) VALUES (?, ?, ?)")
+        .bind(discord_id)
+        .bind(user_name)
+        .bind(STARTING_BUCKS)
+        .execute(pool)
+        .await?
+        .last_insert_id() as i32;
+
+    let user = User {
+        id,
+        discord_id,
+        user_name: user_name.to_string(),
+        bucks: STARTING_BUCKS,
+    };
+    cache.put(user.clone()).await;
+    Ok(user)
+}
+
+/// Creates a wager. `outcome_names` is empty for a flat, fixed-stake wager
+/// (the two implicit outcomes are synthesized later by `settle_wager`); a
+/// non-empty list turns it into a multi-outcome market, with each entry
+/// naming one outcome participants can join and stake on.
+pub async fn create_wager(
+    pool: &Pool<MySql>,
+    amount: i32,
+    outcome_names: &[String],
+) -> Result<(Wager, Vec<Outcome>), sqlx::Error> {
+    let wager_id = sqlx::query("INSERT INTO wager(amount) VALUES (?)")
+        .bind(amount)
+        .execute(pool)
+        .await?
+        .last_insert_id();
+
+    let wager = Wager {
+        id: wager_id as i32,
+        amount,
+        closed: false,
+    };
+
+    let mut outcomes = Vec::with_capacity(outcome_names.len());
+    for name in outcome_names {
+        let id = sqlx::query("INSERT INTO outcome(wager_id, name) VALUES (?, ?)")
+            .bind(wager.id)
+            .bind(name)
+            .execute(pool)
+            .await?
+            .last_insert_id();
+        outcomes.push(Outcome {
+            id: id as i32,
+            wager_id: wager.id,
+            name: name.clone(),
+        });
+    }
+
+    Ok((wager, outcomes))
+}
+
+/// Joins (auto-provisioning if needed) a user to a wager, after checking
+/// they aren't already in it and can cover the stake. `outcome_id` is `None`
+/// for a flat wager (classified into a winner/loser outcome later, at
+/// settlement); `stake` defaults to the wager's fixed `amount` when `None`,
+/// which is every flat join and can also be a market participant accepting
+/// the default stake.
+pub async fn join_wager(
+    pool: &Pool<MySql>,
+    cache: &UserCache,
+    discord_id: u64,
+    user_name: &str,
+    wager_id: i32,
+    outcome_id: Option<i32>,
+    stake: Option<i32>,
+) -> Result<User, JoinError> {
+    let user = get_or_create_user(pool, cache, discord_id, user_name).await?;
+
+    if sqlx::query("SELECT * FROM user_wager WHERE user_id = ? AND wager_id = ?")
+        .bind(user.id)
+        .bind(wager_id)
+        .fetch_optional(pool)
+        .await?
+        .is_some()
+    {
+        return Err(JoinError::AlreadyJoined);
+    }
+
+    let wager = sqlx::query_as::<_, Wager>("SELECT * FROM wager WHERE id = ?")
+        .bind(wager_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(JoinError::WagerNotFound)?;
+
+    let stake = stake.unwrap_or(wager.amount);
+
+    if stake <= 0 {
+        return Err(JoinError::InvalidStake);
+    }
+
+    if user.bucks < stake {
+        return Err(JoinError::InsufficientFunds);
+    }
+
+    sqlx::query(
+        "INSERT INTO user_wager(user_id, wager_id, outcome_id, stake) VALUES (?, ?, ?, ?)",
+    )
+    .bind(user.id)
+    .bind(wager_id)
+    .bind(outcome_id)
+    .bind(stake)
+    .execute(pool)
+    .await?;
+
+    Ok(user)
+}
+
+pub async fn leave_wager(
+    pool: &Pool<MySql>,
+    discord_id: u64,
+    wager_id: i32,
+) -> Result<(), JoinError> {
+    // `user_wager.user_id` is the internal auto-increment `user.id`, not the
+    // Discord snowflake, so it has to be resolved first
+    let user: User = sqlx::query_as::<_, User>("SELECT * FROM user WHERE discord_id = ?")
+        .bind(discord_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(JoinError::WagerNotFound)?;
+
+    let user_wager = sqlx::query_as::<_, UserWager>(
+        "SELECT * FROM user_wager WHERE user_id = ? AND wager_id = ?",
+    )
+    .bind(user.id)
+    .bind(wager_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(JoinError::WagerNotFound)?;
+
+    sqlx::query("DELETE FROM user_wager WHERE id = ?")
+        .bind(user_wager.id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Settles a market: entries staked on `winning_outcome_id` split the losing
+/// entries' stakes proportionally to their own stake share. `settle_wager`
+/// settles its two-outcome special case through the same `settle_locked`
+/// engine rather than calling this.
+pub async fn close_market(
+    pool: &Pool<MySql>,
+    cache: &UserCache,
+    wager_id: i32,
+    winning_outcome_id: i32,
+) -> Result<SucessfullCloseWager, SettleError> {
+    let mut tx = pool.begin().await?;
+
+    let wager = lock_wager(&mut tx, wager_id).await?;
+
+    let winning_outcome: Outcome = sqlx::query_as::<_, Outcome>(
+        "SELECT * FROM outcome WHERE id = ? AND wager_id = ?",
+    )
+    .bind(winning_outcome_id)
+    .bind(wager_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let (entries, users) = load_entries_locked(&mut tx, wager_id).await?;
+    let (winning_users, losing_users) =
+        settle_locked(&mut tx, &entries, users, winning_outcome_id).await?;
+
+    sqlx::query("UPDATE wager SET closed = true WHERE id = ?")
+        .bind(wager_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    for user in winning_users.iter().chain(losing_users.iter()) {
+        cache.put(user.clone()).await;
+    }
+
+    Ok(SucessfullCloseWager {
+        winners: winning_users,
+        losers: losing_users,
+        wager: Wager {
+            closed: true,
+            ..wager
+        },
+        winning_outcome: Some(winning_outcome),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: i64,
+    pub user_name: String,
+    pub discord_id: u64,
+    pub bucks: i32,
+}
+
+/// Users ordered by balance, richest first.
+pub async fn leaderboard(pool: &Pool<MySql>) -> Result<Vec<LeaderboardEntry>, sqlx::Error> {
+    let users = sqlx::query_as::<_, User>("SELECT * FROM user ORDER BY bucks DESC")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(users
+        .into_iter()
+        .enumerate()
+        .map(|(index, user)| LeaderboardEntry {
+            rank: index as i64 + 1,
+            user_name: user.user_name,
+            discord_id: user.discord_id,
+            bucks: user.bucks,
+        })
+        .collect())
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct WagerHistoryEntry {
+    pub wager_id: i32,
+    // this user's own stake, not the wager's overall `amount` (they can
+    // diverge for a market joined through `join_wager`'s explicit `stake`)
+    pub amount: i32,
+    pub closed: bool,
+    pub delta: Option<i32>,
+}
+
+/// Every wager a user has participated in, newest first.
+pub async fn user_history(
+    pool: &Pool<MySql>,
+    discord_id: u64,
+) -> Result<Vec<WagerHistoryEntry>, sqlx::Error> {
+    sqlx::query_as::<_, WagerHistoryEntry>(
+        "SELECT wager.id AS wager_id, user_wager.stake AS amount, wager.closed, user_wager.delta \
+         FROM user_wager \
+         JOIN wager ON wager.id = user_wager.wager_id \
+         JOIN user ON user.id = user_wager.user_id \
+         WHERE user.discord_id = ? \
+         ORDER BY wager.id DESC",
+    )
+    .bind(discord_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribute_pot_conserves_bucks_when_not_evenly_divisible() {
+        // 3 losers staking 10 each = 30 pot, split across 4 winners
+        let pot = 30;
+        let winner_count = 4;
+        let payouts = distribute_pot(pot, winner_count);
+        assert_eq!(payouts.len(), winner_count as usize);
+        assert_eq!(payouts.iter().sum::<i32>(), pot);
+        // remainder goes to the first winner
+        assert_eq!(payouts[0], 9);
+        assert_eq!(&payouts[1..], &[7, 7, 7]);
+    }
+
+    const WINNERS: i32 = 1;
+    const LOSERS: i32 = 2;
+
+    fn entry(id: i32, user_id: i32, outcome_id: i32, stake: i32) -> UserWager {
+        UserWager {
+            id,
+            wager_id: 1,
+            user_id,
+            outcome_id: Some(outcome_id),
+            stake: Some(stake),
+            delta: None,
+        }
+    }
+
+    // there's no DB fixture in this crate to drive `settle_wager` itself, so
+    // this exercises `compute_settlement` directly — the same pre-DB
+    // classification and payout math `settle_locked` runs — for a market
+    // with uneven stakes, rather than a reimplementation of that math
+    #[test]
+    fn compute_settlement_conserves_bucks_across_uneven_stakes() {
+        let entries = vec![
+            entry(1, 1, WINNERS, 30),
+            entry(2, 2, WINNERS, 20),
+            entry(3, 3, WINNERS, 10),
+            entry(4, 4, LOSERS, 15),
+            entry(5, 5, LOSERS, 5),
+        ];
+
+        let (winner_payouts, losing_entries) = compute_settlement(&entries, WINNERS).unwrap();
+
+        let pot: i32 = losing_entries.iter().filter_map(|entry| entry.stake).sum();
+        let payout_total: i32 = winner_payouts.iter().map(|(_, payout)| payout).sum();
+        assert_eq!(payout_total, pot);
+        assert_eq!(losing_entries.len(), 2);
+    }
+
+    // regression for the bug where a wager with losing stakes but no stake
+    // at all on the winning side let `distribute_proportional`'s zero-total
+    // guard zero out every payout while the losers were still going to be
+    // charged, destroying bucks; `compute_settlement` must refuse instead of
+    // handing `settle_locked` a plan that would do that
+    #[test]
+    fn compute_settlement_rejects_wager_with_no_winning_stake() {
+        let entries = vec![entry(1, 1, LOSERS, 100), entry(2, 2, LOSERS, 100)];
+
+        let result = compute_settlement(&entries, WINNERS);
+        assert!(matches!(result, Err(SettleError::NoWinningStake)));
+    }
+}
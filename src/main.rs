@@ -2,33 +2,39 @@ use std::{env, net::SocketAddr};
 
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
     routing::{delete, get, patch, post},
     Json, Router,
 };
+use bean_bucks_api::{
+    auth::{require_read_scope, require_write_scope, AuthConfig},
+    cache::UserCache,
+    jobs::{self, CloseWagerJobPayload},
+    JoinError, Outcome, SettleError, SucessfullCloseWager, User, Wager,
+};
+use chrono::DateTime;
 use dotenv::dotenv;
 use serde::{Deserialize, Serialize};
-use sqlx::{mysql::MySqlPoolOptions, MySql, Pool, Row};
+use sqlx::{mysql::MySqlPoolOptions, MySql, Pool};
 
-#[derive(sqlx::FromRow, Debug, Serialize)]
-struct User {
-    id: i32,
-    discord_id: u64,
-    user_name: String,
-    bucks: i32,
+#[derive(Clone)]
+struct AppState {
+    pool: Pool<MySql>,
+    cache: UserCache,
 }
-#[derive(sqlx::FromRow, Debug, Serialize, Deserialize)]
-struct Wager {
-    id: i32,
-    amount: i32,
-    closed: bool,
+
+impl axum::extract::FromRef<AppState> for Pool<MySql> {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
 }
 
-#[derive(sqlx::FromRow, Debug, Serialize)]
-struct UserWager {
-    id: i32,
-    wager_id: i32,
-    user_id: i32,
+impl axum::extract::FromRef<AppState> for UserCache {
+    fn from_ref(state: &AppState) -> Self {
+        state.cache.clone()
+    }
 }
 
 #[tokio::main]
@@ -39,17 +45,40 @@ async fn main() -> Result<(), sqlx::Error> {
         .max_connections(5)
         .connect(&database_url)
         .await?;
+    let auth_config = AuthConfig::from_env();
+    let cache = UserCache::default();
+    jobs::spawn_worker(pool.clone(), cache.clone());
+
+    let public_routes = Router::new().route("/", get(root));
 
-    let app = Router::new()
-        .route("/", get(root))
+    // read-only: safe for a dashboard to hold a token that can never mint or settle anything
+    let read_routes = Router::new()
         .route("/users", get(list_users))
         .route("/user", get(get_user))
+        .route("/leaderboard", get(leaderboard))
+        .route("/user/history", get(user_history))
+        .route_layer(middleware::from_fn_with_state(
+            auth_config.clone(),
+            require_read_scope,
+        ));
+
+    // mutating: only the Discord bot should hold a token that can reach these
+    let write_routes = Router::new()
         .route("/user", post(create_user))
         .route("/user/wager", post(add_user_to_wager))
         .route("/user/wager", delete(remove_user_from_wager))
         .route("/wager", post(create_wager))
         .route("/wager", patch(close_wager))
-        .with_state(pool);
+        .route("/wager/schedule", post(schedule_wager))
+        .route_layer(middleware::from_fn_with_state(
+            auth_config.clone(),
+            require_write_scope,
+        ));
+
+    let app = public_routes
+        .merge(read_routes)
+        .merge(write_routes)
+        .with_state(AppState { pool, cache });
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
@@ -105,6 +134,7 @@ struct CreateUser {
 
 async fn create_user(
     State(pool): State<Pool<MySql>>,
+    State(cache): State<UserCache>,
     Json(payload): Json<CreateUser>,
 ) -> (StatusCode, String) {
     //search database for discord_id
@@ -121,12 +151,22 @@ async fn create_user(
     //create user
     let res = sqlx::query("INSERT INTO user(discord_id, user_name, bucks) VALUES (?, ?, ?)")
         .bind(payload.discord_id)
-        .bind(payload.user_name)
-        .bind(500)
+        .bind(&payload.user_name)
+        .bind(bean_bucks_api::STARTING_BUCKS)
         .execute(&pool)
         .await;
     match res {
-        Ok(_) => (StatusCode::OK, "user created".to_string()),
+        Ok(result) => {
+            cache
+                .put(User {
+                    id: result.last_insert_id() as i32,
+                    discord_id: payload.discord_id,
+                    user_name: payload.user_name,
+                    bucks: bean_bucks_api::STARTING_BUCKS,
+                })
+                .await;
+            (StatusCode::OK, "user created".to_string())
+        }
         Err(e) => (
             StatusCode::EXPECTATION_FAILED,
             format!("failed to create user: {}", e.to_string()),
@@ -146,229 +186,139 @@ async fn create_user(
 #[derive(Deserialize, Debug)]
 struct CloseWagerPayload {
     wager_id: i32,
+    #[serde(default)]
     winning_user_discord_ids: Vec<u64>,
+    #[serde(default)]
     losing_user_discord_ids: Vec<u64>,
+    // set instead of the winner/loser lists to settle a market created with
+    // `outcomes`: pays out whoever joined this outcome
+    winning_outcome_id: Option<i32>,
 }
-#[derive(Serialize)]
-struct SucessfullCloseWager {
-    winners: Vec<User>,
-    losers: Vec<User>,
-    wager: Wager,
+
+fn empty_close_wager_response(wager: Wager) -> Json<SucessfullCloseWager> {
+    Json(SucessfullCloseWager {
+        winners: vec![],
+        losers: vec![],
+        wager,
+        winning_outcome: None,
+    })
 }
 
 async fn close_wager(
     State(pool): State<Pool<MySql>>,
+    State(cache): State<UserCache>,
     Json(payload): Json<CloseWagerPayload>,
 ) -> (StatusCode, Json<SucessfullCloseWager>) {
-    // get the wager
-    let wager: Option<Wager> = sqlx::query_as::<_, Wager>("SELECT * FROM wager WHERE id = ?")
-        .bind(payload.wager_id)
-        .fetch_optional(&pool)
-        .await
-        .expect("expected wager query to succeed");
-
-    if wager.is_none() {
-        return (
-            StatusCode::EXPECTATION_FAILED,
-            Json(SucessfullCloseWager {
-                winners: vec![],
-                losers: vec![],
-                wager: Wager {
-                    id: 0,
-                    amount: 0,
-                    closed: false,
-                },
-            }),
-        );
-    }
-
-    let wager = wager.unwrap();
-
-    if wager.closed {
-        return (
+    let result = match payload.winning_outcome_id {
+        Some(winning_outcome_id) => {
+            bean_bucks_api::close_market(&pool, &cache, payload.wager_id, winning_outcome_id).await
+        }
+        None => {
+            bean_bucks_api::settle_wager(
+                &pool,
+                &cache,
+                payload.wager_id,
+                &payload.winning_user_discord_ids,
+                &payload.losing_user_discord_ids,
+            )
+            .await
+        }
+    };
+    match result {
+        Ok(result) => (StatusCode::OK, Json(result)),
+        Err(SettleError::WagerNotFound) => (
             StatusCode::EXPECTATION_FAILED,
-            Json(SucessfullCloseWager {
-                winners: vec![],
-                losers: vec![],
-                wager,
+            empty_close_wager_response(Wager {
+                id: 0,
+                amount: 0,
+                closed: false,
             }),
-        );
-    }
-
-    // get the users in the wager
-    let user_ids: Vec<u8> =
-        sqlx::query_as::<_, UserWager>("SELECT * FROM user_wager WHERE wager_id = ?")
-            .bind(payload.wager_id)
-            .fetch_all(&pool)
-            .await
-            .expect("expected user_wager query to succeed")
-            .into_iter()
-            .map(|user_wager| user_wager.user_id as u8)
-            .collect();
-
-    let mut users: Vec<User> = vec![];
-    for user in &user_ids {
-        let user = sqlx::query_as::<_, User>("SELECT * FROM user WHERE id = ?")
-            .bind(user)
-            .fetch_one(&pool)
-            .await
-            .expect("expected user query to succeed");
-        users.push(user);
-    }
-
-    // the users in the payload for winners and losers should be checked to make sure they were
-    // added to the wager. If they were not, they should be ignored
-    let mut winning_users: Vec<User> = vec![];
-    let mut losing_users: Vec<User> = vec![];
-    for user in users {
-        if payload.winning_user_discord_ids.contains(&user.discord_id) {
-            winning_users.push(user);
-        } else if payload.losing_user_discord_ids.contains(&user.discord_id) {
-            losing_users.push(user);
+        ),
+        Err(SettleError::AlreadyClosed) => {
+            let wager = sqlx::query_as::<_, Wager>("SELECT * FROM wager WHERE id = ?")
+                .bind(payload.wager_id)
+                .fetch_one(&pool)
+                .await
+                .expect("expected wager query to succeed");
+            (StatusCode::EXPECTATION_FAILED, empty_close_wager_response(wager))
         }
-    }
-
-    //payout the winners
-    let mut payout = 0;
-    //print out winning users
-    if winning_users.len() > 0 {
-        payout = wager.amount * (losing_users.len() as i32) / winning_users.len() as i32;
-    }
-    for user in &winning_users {
-        let new_bucks = user.bucks + payout;
-        sqlx::query("UPDATE user SET bucks = ? WHERE id = ?")
-            .bind(new_bucks)
-            .bind(user.id)
-            .execute(&pool)
-            .await
-            .expect("expected user update to succeed");
-    }
-
-    //take money from the losers
-    // if they do not have enough money, put them at zero
-    for user in &losing_users {
-        let mut new_bucks = user.bucks - wager.amount;
-        if new_bucks < 0 {
-            new_bucks = 0;
+        Err(SettleError::NoWinningStake) => {
+            let wager = sqlx::query_as::<_, Wager>("SELECT * FROM wager WHERE id = ?")
+                .bind(payload.wager_id)
+                .fetch_one(&pool)
+                .await
+                .expect("expected wager query to succeed");
+            (StatusCode::BAD_REQUEST, empty_close_wager_response(wager))
         }
-        sqlx::query("UPDATE user SET bucks = ? WHERE id = ?")
-            .bind(new_bucks)
-            .bind(user.id)
-            .execute(&pool)
-            .await
-            .expect("expected user update to succeed");
+        Err(SettleError::Database(_)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            empty_close_wager_response(Wager {
+                id: 0,
+                amount: 0,
+                closed: false,
+            }),
+        ),
     }
-
-    //mark the wager as closed
-    sqlx::query("UPDATE wager SET closed = true WHERE id = ?")
-        .bind(payload.wager_id)
-        .execute(&pool)
-        .await
-        .expect("expected wager update to succeed");
-
-    (
-        StatusCode::OK,
-        Json(SucessfullCloseWager {
-            winners: winning_users,
-            losers: losing_users,
-            wager,
-        }),
-    )
 }
 
 #[derive(Deserialize)]
 struct WagerInput {
     amount: i32,
+    // naming outcomes turns this into a multi-outcome market instead of a
+    // flat wager
+    #[serde(default)]
+    outcomes: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct WagerResponse {
+    wager: Wager,
+    outcomes: Vec<Outcome>,
 }
 
 async fn create_wager(
     State(pool): State<Pool<MySql>>,
     Json(payload): Json<WagerInput>,
-) -> (StatusCode, Json<Wager>) {
-    // create a wager and return the id of the wager
-    let wager_id = sqlx::query("INSERT INTO wager(amount) VALUES (?)")
-        .bind(payload.amount)
-        .execute(&pool)
+) -> (StatusCode, Json<WagerResponse>) {
+    let (wager, outcomes) = bean_bucks_api::create_wager(&pool, payload.amount, &payload.outcomes)
         .await
-        .expect("excpect wager to successfull be created")
-        .last_insert_id();
-
-    // return the wager id
-    (
-        StatusCode::OK,
-        Json(Wager {
-            id: wager_id as i32,
-            amount: payload.amount,
-            closed: false,
-        }),
-    )
+        .expect("excpect wager to successfull be created");
+    (StatusCode::OK, Json(WagerResponse { wager, outcomes }))
 }
 #[derive(Deserialize, Serialize)]
 struct UserForWagerPayload {
     discord_id: u64,
     user_name: String,
     wager_id: i32,
+    // set to join one outcome of a market rather than a flat wager
+    outcome_id: Option<i32>,
+    // defaults to the wager's fixed amount when not set
+    stake: Option<i32>,
 }
 async fn add_user_to_wager(
     State(pool): State<Pool<MySql>>,
+    State(cache): State<UserCache>,
     Json(payload): Json<UserForWagerPayload>,
 ) -> (StatusCode, Json<UserForWagerPayload>) {
-    // get the user id from the discord id
-    // if the user does not exist, create them with an amount of 500
-    let user_id: i32 = match sqlx::query("SELECT id from user where discord_id = ?")
-        .bind(payload.discord_id)
-        .fetch_optional(&pool)
-        .await
-        .expect("expected user query to succeed")
-        .map(|row| row.try_get("id").expect("expected user id to be an i32"))
-    {
-        Some(id) => id,
-        None => sqlx::query("INSERT INTO user(discord_id, user_name, bucks) VALUES (?, ?, ?)")
-            .bind(payload.discord_id)
-            .bind(&payload.user_name)
-            .bind(500)
-            .execute(&pool)
-            .await
-            .expect("expected user insert to succeed")
-            .last_insert_id() as i32,
-    };
-
-    //check if user is already in the wager
-    if sqlx::query("SELECT * FROM user_wager WHERE user_id = ? AND wager_id = ?")
-        .bind(user_id)
-        .bind(payload.wager_id)
-        .fetch_optional(&pool)
-        .await
-        .expect("expected user_wager query to succeed")
-        .is_some()
+    match bean_bucks_api::join_wager(
+        &pool,
+        &cache,
+        payload.discord_id,
+        &payload.user_name,
+        payload.wager_id,
+        payload.outcome_id,
+        payload.stake,
+    )
+    .await
     {
-        return (StatusCode::OK, Json(payload));
-    }
-
-    //check if user has enough money to join wager
-    let user = sqlx::query_as::<_, User>("SELECT * FROM user WHERE id = ?")
-        .bind(user_id)
-        .fetch_one(&pool)
-        .await
-        .expect("expected user query to succeed");
-    let wager = sqlx::query_as::<_, Wager>("SELECT * FROM wager WHERE id = ?")
-        .bind(payload.wager_id)
-        .fetch_one(&pool)
-        .await
-        .expect("expected wager query to succeed");
-    if user.bucks < wager.amount {
-        return (StatusCode::BAD_REQUEST, Json(payload));
+        Ok(_) | Err(JoinError::AlreadyJoined) => (StatusCode::OK, Json(payload)),
+        Err(JoinError::InsufficientFunds) => (StatusCode::BAD_REQUEST, Json(payload)),
+        Err(JoinError::InvalidStake) => (StatusCode::BAD_REQUEST, Json(payload)),
+        Err(JoinError::WagerNotFound) => (StatusCode::EXPECTATION_FAILED, Json(payload)),
+        Err(JoinError::Database(e)) => {
+            panic!("expected join_wager to succeed: {e}")
+        }
     }
-
-    // insert the user into the wager
-    sqlx::query("INSERT INTO user_wager(user_id, wager_id) VALUES (?, ?)")
-        .bind(user_id)
-        .bind(payload.wager_id)
-        .execute(&pool)
-        .await
-        .expect("expected user_wager query to succeed");
-
-    return (StatusCode::OK, Json(payload));
 }
 #[derive(Deserialize, Serialize)]
 struct RemoveUserWagerPayload {
@@ -379,23 +329,116 @@ async fn remove_user_from_wager(
     State(pool): State<Pool<MySql>>,
     Json(payload): Json<RemoveUserWagerPayload>,
 ) -> (StatusCode, Json<RemoveUserWagerPayload>) {
-    //check if user is in the wager. If they are, remove them
-    if let Some(user_wager) = sqlx::query_as::<_, UserWager>(
-        "SELECT * FROM user_wager WHERE user_id = ? AND wager_id = ?",
+    match bean_bucks_api::leave_wager(&pool, payload.discord_id, payload.wager_id).await {
+        Ok(()) => (StatusCode::OK, Json(payload)),
+        Err(_) => (StatusCode::BAD_REQUEST, Json(payload)),
+    }
+}
+
+#[derive(Deserialize)]
+struct ScheduleWagerPayload {
+    wager_id: i32,
+    winning_user_discord_ids: Vec<u64>,
+    losing_user_discord_ids: Vec<u64>,
+    // RFC 3339 timestamp of when the wager should auto-settle
+    run_at: String,
+}
+
+async fn schedule_wager(
+    State(pool): State<Pool<MySql>>,
+    Json(payload): Json<ScheduleWagerPayload>,
+) -> (StatusCode, String) {
+    let run_at = match DateTime::parse_from_rfc3339(&payload.run_at) {
+        Ok(run_at) => run_at.naive_utc(),
+        Err(_) => return (StatusCode::BAD_REQUEST, "run_at must be RFC 3339".to_string()),
+    };
+
+    let job_payload = CloseWagerJobPayload {
+        wager_id: payload.wager_id,
+        winning_user_discord_ids: payload.winning_user_discord_ids,
+        losing_user_discord_ids: payload.losing_user_discord_ids,
+    };
+
+    match jobs::schedule_close_wager(&pool, &job_payload, run_at).await {
+        Ok(job_id) => (StatusCode::OK, format!("scheduled job {job_id}")),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to schedule job: {e}"),
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+fn wants_csv(format: &Option<String>) -> bool {
+    format.as_deref() == Some("csv")
+}
+
+fn to_csv<T: Serialize>(rows: &[T]) -> String {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for row in rows {
+        writer
+            .serialize(row)
+            .expect("expected row to serialize to csv");
+    }
+    String::from_utf8(
+        writer
+            .into_inner()
+            .expect("expected csv writer to flush"),
     )
-    .bind(payload.discord_id)
-    .bind(payload.wager_id)
-    .fetch_optional(&pool)
-    .await
-    .expect("expected user_wager query to succeed")
-    {
-        sqlx::query("DELETE FROM user_wager WHERE id = ?")
-            .bind(user_wager.id)
-            .execute(&pool)
-            .await
-            .expect("expected user_wager delete to succeed");
-        return (StatusCode::OK, Json(payload));
+    .expect("expected csv output to be valid utf8")
+}
+
+fn csv_attachment(filename: &str, body: String) -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+async fn leaderboard(
+    State(pool): State<Pool<MySql>>,
+    Query(params): Query<FormatQuery>,
+) -> Response {
+    let entries = bean_bucks_api::leaderboard(&pool)
+        .await
+        .expect("expected leaderboard query to succeed");
+
+    if wants_csv(&params.format) {
+        csv_attachment("leaderboard.csv", to_csv(&entries))
+    } else {
+        Json(entries).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct UserHistoryParams {
+    discord_id: u64,
+    format: Option<String>,
+}
+
+async fn user_history(
+    State(pool): State<Pool<MySql>>,
+    Query(params): Query<UserHistoryParams>,
+) -> Response {
+    let entries = bean_bucks_api::user_history(&pool, params.discord_id)
+        .await
+        .expect("expected user history query to succeed");
+
+    if wants_csv(&params.format) {
+        csv_attachment("history.csv", to_csv(&entries))
     } else {
-        return (StatusCode::BAD_REQUEST, Json(payload));
+        Json(entries).into_response()
     }
 }